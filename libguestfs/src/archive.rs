@@ -0,0 +1,264 @@
+//! Tar/cpio import-export, including flattening a stack of OCI-style
+//! compressed tar layers into a guest directory.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use eyre::Result;
+use libguestfs_sys::*;
+
+use crate::GuestFS;
+use crate::FileType;
+
+/// The compression a tar archive is stored under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    fn as_guestfs_opt(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip"),
+            Compression::Bzip2 => Some("bzip2"),
+            Compression::Xz => Some("xz"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+
+    /// Guesses the compression of a layer tarball from its file extension.
+    fn from_extension<P: AsRef<Path>>(path: P) -> Compression {
+        let path = path.as_ref();
+        let lower = path
+            .file_name()
+            .unwrap_or_else(|| OsStr::new(""))
+            .to_string_lossy()
+            .to_lowercase();
+
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Compression::Gzip
+        } else if lower.ends_with(".tar.bz2") {
+            Compression::Bzip2
+        } else if lower.ends_with(".tar.xz") {
+            Compression::Xz
+        } else if lower.ends_with(".tar.zst") {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// The prefix OCI image layers use to mark a deleted path.
+const WHITEOUT_PREFIX: &str = ".wh.";
+/// The marker OCI image layers use to mean "this directory's prior contents
+/// are fully replaced".
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+impl GuestFS {
+    /// Extracts a host-side tar archive into a guest directory.
+    pub fn tar_in<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        tarfile: P,
+        directory: Q,
+        compression: Compression,
+    ) -> Result<()> {
+        let tarfile = Self::path_to_cstring_host(tarfile)?;
+        let directory = Self::path_to_cstring_guest(directory)?;
+
+        let mut optargs: guestfs_tar_in_opts = unsafe { std::mem::zeroed() };
+        let mut bitmask: u64 = 0;
+        let compress_cstring;
+        if let Some(compress) = compression.as_guestfs_opt() {
+            compress_cstring = Self::path_to_cstring_guest(compress)?;
+            optargs.compress = compress_cstring.as_ptr();
+            bitmask |= GUESTFS_TAR_IN_OPTS_COMPRESS_BITMASK as u64;
+        }
+        optargs.bitmask = bitmask;
+
+        let out = unsafe {
+            guestfs_tar_in_opts(self.handle, tarfile.as_ptr(), directory.as_ptr(), &optargs)
+        };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Archives a guest directory into a host-side tar file.
+    pub fn tar_out<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        directory: P,
+        tarfile: Q,
+        compression: Compression,
+    ) -> Result<()> {
+        let directory = Self::path_to_cstring_guest(directory)?;
+        let tarfile = Self::path_to_cstring_host(tarfile)?;
+
+        let mut optargs: guestfs_tar_out_opts = unsafe { std::mem::zeroed() };
+        let mut bitmask: u64 = 0;
+        let compress_cstring;
+        if let Some(compress) = compression.as_guestfs_opt() {
+            compress_cstring = Self::path_to_cstring_guest(compress)?;
+            optargs.compress = compress_cstring.as_ptr();
+            bitmask |= GUESTFS_TAR_OUT_OPTS_COMPRESS_BITMASK as u64;
+        }
+        optargs.bitmask = bitmask;
+
+        let out = unsafe {
+            guestfs_tar_out_opts(self.handle, directory.as_ptr(), tarfile.as_ptr(), &optargs)
+        };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Extracts a host-side cpio archive into a guest directory.
+    pub fn cpio_in<P: AsRef<Path>, Q: AsRef<Path>>(&self, cpiofile: P, directory: Q) -> Result<()> {
+        let cpiofile = Self::path_to_cstring_host(cpiofile)?;
+        let directory = Self::path_to_cstring_guest(directory)?;
+        let out = unsafe { guestfs_cpio_in(self.handle, cpiofile.as_ptr(), directory.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Archives a guest directory into a host-side cpio file.
+    pub fn cpio_out<P: AsRef<Path>, Q: AsRef<Path>>(&self, directory: P, cpiofile: Q) -> Result<()> {
+        let directory = Self::path_to_cstring_guest(directory)?;
+        let cpiofile = Self::path_to_cstring_host(cpiofile)?;
+        let out = unsafe { guestfs_cpio_out(self.handle, directory.as_ptr(), cpiofile.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Extracts an ordered list of (optionally compressed) tar layers into
+    /// `target`, applying them in sequence and honoring OCI whiteout files:
+    /// a `.wh.<name>` entry deletes `<name>` from the layers below it, and
+    /// `.wh..wh..opq` clears a directory's *prior* contents (not whatever
+    /// the same layer placed alongside the marker).
+    pub fn unpack_layers<P: AsRef<Path>>(&self, layers: &[P], target: &Path) -> Result<()> {
+        for layer in layers {
+            let layer = layer.as_ref();
+            let members = list_tar_members(layer)?;
+            self.tar_in(layer, target, Compression::from_extension(layer))?;
+            self.apply_whiteouts(target, target, &members)?;
+        }
+        Ok(())
+    }
+
+    fn apply_whiteouts(
+        &self,
+        target: &Path,
+        dir: &Path,
+        added_by_layer: &HashSet<PathBuf>,
+    ) -> Result<()> {
+        let entries: Vec<_> = self
+            .read_dir(dir)?
+            .into_iter()
+            .filter(|entry| entry.name != "." && entry.name != "..")
+            .collect();
+
+        let mut entries = entries;
+        if entries.iter().any(|entry| entry.name == OPAQUE_WHITEOUT) {
+            // Walk down through the entries this layer re-added, so that
+            // earlier-layer files inside a re-added subdirectory go too.
+            let mut pending = vec![dir.to_path_buf()];
+            while let Some(current) = pending.pop() {
+                let relative = current.strip_prefix(target).unwrap_or(&current).to_path_buf();
+                for entry in self.read_dir(&current)? {
+                    if entry.name == "." || entry.name == ".." || entry.name == OPAQUE_WHITEOUT {
+                        continue;
+                    }
+                    let path = current.join(&entry.name);
+                    if !added_by_layer.contains(&relative.join(&entry.name)) {
+                        self.remove_path(&path)?;
+                    } else if entry.file_type == FileType::Directory {
+                        pending.push(path);
+                    }
+                }
+            }
+            self.remove_file(dir.join(OPAQUE_WHITEOUT))?;
+
+            // Re-read: the only entries left are the ones this layer added
+            // alongside the opaque marker, and one of those may itself carry
+            // a `.wh.<name>` for something the same layer recreated and then
+            // partially emptied in one go.
+            entries = self
+                .read_dir(dir)?
+                .into_iter()
+                .filter(|entry| entry.name != "." && entry.name != "..")
+                .collect();
+        }
+
+        for entry in &entries {
+            if let Some(shadowed) = entry.name.strip_prefix(WHITEOUT_PREFIX) {
+                self.remove_path(&dir.join(shadowed))?;
+                self.remove_file(dir.join(&entry.name))?;
+            }
+        }
+
+        // Re-read the directory: a `.wh.<name>` above may have just removed one
+        // of the entries in `entries`, so recursing over that stale snapshot
+        // could walk into a directory that no longer exists.
+        let remaining_entries = self
+            .read_dir(dir)?
+            .into_iter()
+            .filter(|entry| entry.name != "." && entry.name != "..");
+        for entry in remaining_entries {
+            if entry.file_type == FileType::Directory {
+                self.apply_whiteouts(target, &dir.join(&entry.name), added_by_layer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes a guest path whether it is a file or a directory tree.
+    fn remove_path(&self, path: &Path) -> Result<()> {
+        let path_cstring = Self::path_to_cstring_guest(path)?;
+        let out = unsafe { guestfs_rm_rf(self.handle, path_cstring.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+}
+
+/// Lists the member paths of a host-side tar archive (relative, no leading
+/// `/`, directories without a trailing `/`), so whiteout handling can tell
+/// "added by this layer" apart from "inherited from an earlier layer".
+fn list_tar_members(layer: &Path) -> Result<HashSet<PathBuf>> {
+    let output = std::process::Command::new("tar")
+        .arg("-tf")
+        .arg(layer)
+        .output()?;
+    if !output.status.success() {
+        return Err(eyre::eyre!(
+            "failed to list members of tar layer {}: {}",
+            layer.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let listing = String::from_utf8(output.stdout)?;
+    Ok(listing
+        .lines()
+        .map(|entry| PathBuf::from(entry.trim_end_matches('/')))
+        .collect())
+}
@@ -0,0 +1,198 @@
+//! A safe builder over `guestfs_add_drive_opts`, for attaching drives by
+//! explicit format, over NBD/iSCSI/RBD, or with a specific cache mode.
+
+use std::path::Path;
+
+use eyre::Result;
+use libguestfs_sys::*;
+
+use crate::GuestFS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveFormat {
+    Raw,
+    Qcow2,
+    Vmdk,
+    Vdi,
+}
+
+impl DriveFormat {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DriveFormat::Raw => "raw",
+            DriveFormat::Qcow2 => "qcow2",
+            DriveFormat::Vmdk => "vmdk",
+            DriveFormat::Vdi => "vdi",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriveProtocol {
+    File,
+    Nbd,
+    Iscsi,
+    Rbd,
+}
+
+impl DriveProtocol {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DriveProtocol::File => "file",
+            DriveProtocol::Nbd => "nbd",
+            DriveProtocol::Iscsi => "iscsi",
+            DriveProtocol::Rbd => "rbd",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    None,
+    Writeback,
+    Unsafe,
+}
+
+impl CacheMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CacheMode::None => "none",
+            CacheMode::Writeback => "writeback",
+            CacheMode::Unsafe => "unsafe",
+        }
+    }
+}
+
+/// A fluent builder around `guestfs_add_drive_opts`, setting the optargs
+/// bitmask bit for only the fields the caller actually configured.
+#[derive(Default)]
+pub struct AddDriveOptions {
+    format: Option<DriveFormat>,
+    readonly: Option<bool>,
+    protocol: Option<DriveProtocol>,
+    servers: Vec<String>,
+    exportname: Option<String>,
+    cachemode: Option<CacheMode>,
+    discard: Option<bool>,
+}
+
+impl AddDriveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn format(mut self, format: DriveFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    pub fn readonly(mut self, readonly: bool) -> Self {
+        self.readonly = Some(readonly);
+        self
+    }
+
+    pub fn protocol(mut self, protocol: DriveProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// Adds a server to the NBD/iSCSI/RBD server list, e.g. `"nbd.example.com:10809"`.
+    pub fn server<S: Into<String>>(mut self, server: S) -> Self {
+        self.servers.push(server.into());
+        self
+    }
+
+    /// The NBD export name, or the iSCSI/RBD volume path.
+    pub fn exportname<S: Into<String>>(mut self, exportname: S) -> Self {
+        self.exportname = Some(exportname.into());
+        self
+    }
+
+    pub fn cachemode(mut self, cachemode: CacheMode) -> Self {
+        self.cachemode = Some(cachemode);
+        self
+    }
+
+    pub fn discard(mut self, discard: bool) -> Self {
+        self.discard = Some(discard);
+        self
+    }
+
+    /// Attaches the drive.
+    pub fn add<P: AsRef<Path>>(self, g: &GuestFS, drive: P) -> Result<i32> {
+        let drive = if self.protocol.is_none() || self.protocol == Some(DriveProtocol::File) {
+            GuestFS::path_to_cstring_host(drive)?
+        } else {
+            GuestFS::path_to_cstring_guest(drive)?
+        };
+
+        let mut optargs: guestfs_add_drive_opts = unsafe { std::mem::zeroed() };
+        let mut bitmask: u64 = 0;
+
+        let format_cstring;
+        if let Some(format) = self.format {
+            format_cstring = GuestFS::path_to_cstring_guest(format.as_str())?;
+            optargs.format = format_cstring.as_ptr();
+            bitmask |= GUESTFS_ADD_DRIVE_OPTS_FORMAT_BITMASK as u64;
+        }
+
+        if let Some(readonly) = self.readonly {
+            optargs.readonly = readonly as i32;
+            bitmask |= GUESTFS_ADD_DRIVE_OPTS_READONLY_BITMASK as u64;
+        }
+
+        let protocol_cstring;
+        if let Some(protocol) = self.protocol {
+            protocol_cstring = GuestFS::path_to_cstring_guest(protocol.as_str())?;
+            optargs.protocol = protocol_cstring.as_ptr();
+            bitmask |= GUESTFS_ADD_DRIVE_OPTS_PROTOCOL_BITMASK as u64;
+        }
+
+        let server_cstrings = self
+            .servers
+            .iter()
+            .map(GuestFS::path_to_cstring_guest)
+            .collect::<Result<Vec<_>>>()?;
+        let mut server_ptrs: Vec<*const i8> =
+            server_cstrings.iter().map(|s| s.as_ptr()).collect();
+        if !server_ptrs.is_empty() {
+            server_ptrs.push(std::ptr::null());
+            optargs.server = server_ptrs.as_ptr() as *mut *const i8;
+            bitmask |= GUESTFS_ADD_DRIVE_OPTS_SERVER_BITMASK as u64;
+        }
+
+        let exportname_cstring;
+        if let Some(exportname) = &self.exportname {
+            exportname_cstring = GuestFS::path_to_cstring_guest(exportname)?;
+            optargs.exportname = exportname_cstring.as_ptr();
+            bitmask |= GUESTFS_ADD_DRIVE_OPTS_EXPORTNAME_BITMASK as u64;
+        }
+
+        let cachemode_cstring;
+        if let Some(cachemode) = self.cachemode {
+            cachemode_cstring = GuestFS::path_to_cstring_guest(cachemode.as_str())?;
+            optargs.cachemode = cachemode_cstring.as_ptr();
+            bitmask |= GUESTFS_ADD_DRIVE_OPTS_CACHEMODE_BITMASK as u64;
+        }
+
+        let discard_cstring;
+        if let Some(discard) = self.discard {
+            discard_cstring = GuestFS::path_to_cstring_guest(if discard {
+                "besteffort"
+            } else {
+                "disable"
+            })?;
+            optargs.discard = discard_cstring.as_ptr();
+            bitmask |= GUESTFS_ADD_DRIVE_OPTS_DISCARD_BITMASK as u64;
+        }
+
+        optargs.bitmask = bitmask;
+
+        let out = unsafe { guestfs_add_drive_opts(g.handle, drive.as_ptr(), &optargs) };
+        if out == 0 {
+            Ok(out)
+        } else {
+            g.check_error()
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! A `std::fs`-like API over the guest filesystem exposed by a [`GuestFS`] handle.
+
+use std::path::Path;
+
+use eyre::Result;
+use libguestfs_sys::*;
+
+use crate::GuestFS;
+
+/// The type of a guest directory entry, decoded from the `ftyp` byte returned
+/// by `guestfs_readdir`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    RegularFile,
+    Directory,
+    Symlink,
+    BlockDevice,
+    CharDevice,
+    Fifo,
+    Socket,
+    Unknown,
+}
+
+impl FileType {
+    fn from_ftyp(ftyp: i8) -> Self {
+        match ftyp as u8 as char {
+            'r' => FileType::RegularFile,
+            'd' => FileType::Directory,
+            'l' => FileType::Symlink,
+            'b' => FileType::BlockDevice,
+            'c' => FileType::CharDevice,
+            'f' => FileType::Fifo,
+            's' => FileType::Socket,
+            _ => FileType::Unknown,
+        }
+    }
+}
+
+/// A single entry returned by [`GuestFS::read_dir`].
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub name: String,
+    pub inode: i64,
+    pub file_type: FileType,
+}
+
+/// A chunked reader/writer builder for streaming large guest files through
+/// `guestfs_pread`/`guestfs_pwrite` instead of loading them wholesale.
+pub struct OpenOptions {
+    buffer_size: usize,
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        OpenOptions {
+            buffer_size: 1024 * 1024,
+        }
+    }
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the chunk size used for `read`/`write`, in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer_size` is zero, since `read`/`write` would otherwise
+    /// make no progress (`read`) or panic deep inside `contents.chunks`
+    /// (`write`).
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        assert!(buffer_size > 0, "buffer_size must be greater than zero");
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Streams the whole guest file at `path` into memory, one chunk at a time.
+    pub fn read<P: AsRef<Path>>(&self, g: &GuestFS, path: P) -> Result<Vec<u8>> {
+        let path = GuestFS::path_to_cstring_guest_path(path)?;
+        let mut out = Vec::new();
+        let mut offset: i64 = 0;
+
+        loop {
+            let mut size: usize = 0;
+            let chunk_ptr = unsafe {
+                guestfs_pread(
+                    g.handle,
+                    path.as_ptr(),
+                    self.buffer_size as i32,
+                    offset,
+                    &mut size,
+                )
+            };
+            if chunk_ptr.is_null() {
+                return g.check_error();
+            }
+            let chunk = unsafe { std::slice::from_raw_parts(chunk_ptr as *const u8, size) };
+            out.extend_from_slice(chunk);
+            g.free(chunk_ptr as *mut i8);
+
+            if size == 0 {
+                break;
+            }
+            offset += size as i64;
+            if size < self.buffer_size {
+                break;
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Streams `contents` into the guest file at `path`, one chunk at a time,
+    /// creating or truncating the file first.
+    ///
+    /// `guestfs_pwrite` may write fewer bytes than requested, so each chunk is
+    /// retried from the short-write point until it is fully written.
+    pub fn write<P: AsRef<Path>>(&self, g: &GuestFS, path: P, contents: &[u8]) -> Result<()> {
+        // `guestfs_pwrite` only opens an existing file, so create it (empty) first.
+        g.write(path.as_ref(), &[])?;
+        let path = GuestFS::path_to_cstring_guest_path(path)?;
+        let mut offset: i64 = 0;
+
+        for chunk in contents.chunks(self.buffer_size) {
+            let mut written_in_chunk: usize = 0;
+            while written_in_chunk < chunk.len() {
+                let remaining = &chunk[written_in_chunk..];
+                let written = unsafe {
+                    guestfs_pwrite(
+                        g.handle,
+                        path.as_ptr(),
+                        remaining.as_ptr() as *const i8,
+                        remaining.len(),
+                        offset,
+                    )
+                };
+                if written < 0 {
+                    return g.check_error();
+                }
+                written_in_chunk += written as usize;
+                offset += written as i64;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl GuestFS {
+    /// Reads the whole contents of a guest file into memory.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let path = Self::path_to_cstring_guest_path(path)?;
+        let mut size: usize = 0;
+        let out_ptr = unsafe { guestfs_read_file(self.handle, path.as_ptr(), &mut size) };
+        if out_ptr.is_null() {
+            return self.check_error();
+        }
+        let out = unsafe { std::slice::from_raw_parts(out_ptr as *const u8, size) }.to_vec();
+        self.free(out_ptr as *mut i8);
+        Ok(out)
+    }
+
+    /// Reads the whole contents of a guest file into a `String`.
+    pub fn read_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String> {
+        let bytes = self.read(path)?;
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    /// Writes `contents` to a guest file, creating or truncating it first.
+    pub fn write<P: AsRef<Path>>(&self, path: P, contents: &[u8]) -> Result<()> {
+        let path = Self::path_to_cstring_guest_path(path)?;
+        let out = unsafe {
+            guestfs_write(
+                self.handle,
+                path.as_ptr(),
+                contents.as_ptr() as *const i8,
+                contents.len(),
+            )
+        };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Lists the entries of a guest directory, mirroring `std::fs::read_dir`.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<DirEntry>> {
+        let path = Self::path_to_cstring_guest_path(path)?;
+        let list_ptr = unsafe { guestfs_readdir(self.handle, path.as_ptr()) };
+        if list_ptr.is_null() {
+            return self.check_error();
+        }
+
+        let entries =
+            unsafe { std::slice::from_raw_parts((*list_ptr).val, (*list_ptr).len as usize) };
+
+        let mut out = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let name = unsafe { std::ffi::CStr::from_ptr(entry.name) }
+                .to_str()?
+                .to_string();
+            out.push(DirEntry {
+                name,
+                inode: entry.ino,
+                file_type: FileType::from_ftyp(entry.ftyp),
+            });
+        }
+
+        unsafe { guestfs_free_dirent_list(list_ptr) };
+        Ok(out)
+    }
+
+    /// Creates a guest directory and any missing parent directories.
+    pub fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = Self::path_to_cstring_guest_path(path)?;
+        let out = unsafe { guestfs_mkdir_p(self.handle, path.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Removes a single guest file.
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = Self::path_to_cstring_guest_path(path)?;
+        let out = unsafe { guestfs_rm(self.handle, path.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Renames (moves) a guest file or directory.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        let from = Self::path_to_cstring_guest_path(from)?;
+        let to = Self::path_to_cstring_guest_path(to)?;
+        let out = unsafe { guestfs_mv(self.handle, from.as_ptr(), to.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Creates a guest symlink at `link` pointing at `target`.
+    pub fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, target: P, link: Q) -> Result<()> {
+        // `target` need not be absolute: guest symlinks may legitimately point
+        // at a relative path, resolved from the symlink's own directory.
+        let target = Self::path_to_cstring_guest(target)?;
+        let link = Self::path_to_cstring_guest_path(link)?;
+        let out = unsafe { guestfs_ln_s(self.handle, target.as_ptr(), link.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+}
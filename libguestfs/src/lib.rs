@@ -5,6 +5,17 @@ use std::path::Path;
 use eyre::{eyre, Result};
 use libguestfs_sys::*;
 
+mod archive;
+mod drive;
+mod fs;
+mod mkfs;
+mod mount;
+pub use archive::Compression;
+pub use drive::{AddDriveOptions, CacheMode, DriveFormat, DriveProtocol};
+pub use fs::{DirEntry, FileType, OpenOptions};
+pub use mkfs::{BtrfsRaidProfile, FsType, MkfsBuilder};
+pub use mount::GuestOs;
+
 pub struct GuestFS {
     handle: *mut guestfs_h,
     launched: bool,
@@ -285,6 +296,22 @@ impl GuestFS {
         Ok(path)
     }
 
+    /// Like [`GuestFS::path_to_cstring_guest`], but for arguments that are
+    /// actually guest filesystem paths (as opposed to device specifiers,
+    /// option names, etc. that also happen to be converted through
+    /// `path_to_cstring_guest`) and so must be absolute.
+    fn path_to_cstring_guest_path<P: AsRef<Path>>(path: P) -> Result<CString> {
+        let path = path.as_ref();
+        if !path.is_absolute() {
+            return Err(LibGuestFsError::NotAbsolute {
+                errno: 0,
+                message: format!("guest path is not absolute: {}", path.display()),
+            }
+            .into());
+        }
+        Self::path_to_cstring_guest(path)
+    }
+
     // I am going to commit acts of violence
     fn free(&self, pointer: *mut i8) {
         unsafe {
@@ -302,11 +329,9 @@ impl GuestFS {
     fn check_error<T>(&self) -> Result<T> {
         let errno = self.last_errno()?;
         let message = self.last_error()?;
-        dbg!(LibGuestFsError::GuestFsError {
-            errno,
-            message: message.clone()
-        });
-        Err(LibGuestFsError::GuestFsError { errno, message }.into())
+        let err = LibGuestFsError::from_errno(errno, message);
+        dbg!(&err);
+        Err(err.into())
     }
 }
 
@@ -371,10 +396,54 @@ impl StatNS {
     }
 }
 
+// Linux errno values, named here since the crate doesn't otherwise depend on libc.
+const ENOENT: i32 = 2;
+const EPERM: i32 = 1;
+const EACCES: i32 = 13;
+const EISDIR: i32 = 21;
+const ENOTDIR: i32 = 20;
+const ENODEV: i32 = 19;
+const ENOSYS: i32 = 38;
+const ENODATA: i32 = 61;
+
 #[derive(thiserror::Error, Debug)]
 pub enum LibGuestFsError {
+    #[error("no such file or directory: {message} (errno={errno})")]
+    NotFound { errno: i32, message: String },
+    #[error("is a directory: {message} (errno={errno})")]
+    IsADirectory { errno: i32, message: String },
+    #[error("not a directory: {message} (errno={errno})")]
+    NotADirectory { errno: i32, message: String },
+    #[error("path is not absolute: {message} (errno={errno})")]
+    NotAbsolute { errno: i32, message: String },
+    #[error("permission denied: {message} (errno={errno})")]
+    PermissionDenied { errno: i32, message: String },
+    #[error("end of file: {message} (errno={errno})")]
+    EndOfFile { errno: i32, message: String },
+    #[error("unsupported operation: {message} (errno={errno})")]
+    UnsupportedOperation { errno: i32, message: String },
+    #[error("invalid device: {message} (errno={errno})")]
+    InvalidDevice { errno: i32, message: String },
     #[error("libguestfs error: {message} (errno={errno})")]
-    GuestFsError { errno: i32, message: String },
+    Other { errno: i32, message: String },
+}
+
+impl LibGuestFsError {
+    fn from_errno(errno: i32, message: String) -> Self {
+        match errno {
+            ENOENT => LibGuestFsError::NotFound { errno, message },
+            EISDIR => LibGuestFsError::IsADirectory { errno, message },
+            ENOTDIR => LibGuestFsError::NotADirectory { errno, message },
+            // EINVAL is libguestfs's generic "invalid argument" errno, reused for many
+            // unrelated validation failures, not just non-absolute paths, so it isn't
+            // mapped here; those failures surface as `Other` instead.
+            EACCES | EPERM => LibGuestFsError::PermissionDenied { errno, message },
+            ENODATA => LibGuestFsError::EndOfFile { errno, message },
+            ENOSYS => LibGuestFsError::UnsupportedOperation { errno, message },
+            ENODEV => LibGuestFsError::InvalidDevice { errno, message },
+            _ => LibGuestFsError::Other { errno, message },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -536,6 +605,394 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_write_read_round_trip() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        let path = Path::join(Path::new("/"), "test_write_read.txt");
+        g.write(&path, b"hello guest")?;
+
+        assert_eq!(b"hello guest".to_vec(), g.read(&path)?);
+        assert_eq!("hello guest", g.read_to_string(&path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_options_round_trip_chunked() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        // A small buffer_size forces read/write to span several chunks,
+        // exercising the chunk-boundary handling and pwrite's short-write
+        // retry loop.
+        let opts = OpenOptions::new().buffer_size(4);
+        let path = Path::join(Path::new("/"), "test_open_options_round_trip.txt");
+        let contents: Vec<u8> = (0..37).map(|i| b'a' + (i % 26) as u8).collect();
+
+        opts.write(&g, &path, &contents)?;
+        assert_eq!(contents, opts.read(&g, &path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_dir() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        g.touch("/test_read_dir.txt")?;
+        g.create_dir_all("/test_read_dir_subdir")?;
+
+        let entries = g.read_dir("/")?;
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert!(names.contains(&"test_read_dir.txt"));
+        assert!(names.contains(&"test_read_dir_subdir"));
+
+        let file_entry = entries
+            .iter()
+            .find(|entry| entry.name == "test_read_dir.txt")
+            .unwrap();
+        assert_eq!(FileType::RegularFile, file_entry.file_type);
+
+        let dir_entry = entries
+            .iter()
+            .find(|entry| entry.name == "test_read_dir_subdir")
+            .unwrap();
+        assert_eq!(FileType::Directory, dir_entry.file_type);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_dir_rejects_relative_path() {
+        let g = GuestFS::new();
+        let err = g.read_dir("relative/dir").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LibGuestFsError>(),
+            Some(LibGuestFsError::NotAbsolute { .. })
+        ));
+    }
+
+    #[test]
+    fn test_mountpoints_and_is_mounted() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        let mountpoints = g.mountpoints()?;
+        assert_eq!(vec![("/dev/sda".to_string(), PathBuf::from("/"))], mountpoints);
+
+        assert!(g.is_mounted("/")?);
+        assert!(!g.is_mounted("/nope")?);
+
+        let mounts = g.mounts()?;
+        assert_eq!(vec!["/dev/sda".to_string()], mounts);
+
+        g.umount_all()?;
+        assert!(!g.is_mounted("/")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_os_no_os() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        // A bare ext4 filesystem with no installed OS has nothing for
+        // inspection to find.
+        assert!(g.inspect_os()?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_mount_no_os() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+
+        assert!(g.auto_mount(false).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mkfs_builder_ext4() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+
+        MkfsBuilder::new(FsType::Ext4)
+            .block_size(4096)
+            .label("test-label")
+            .create(&g, "/dev/sda")?;
+
+        let filesystems = g.list_filesystems()?;
+        assert_eq!("/dev/sda", filesystems[0]);
+        assert_eq!("ext4", filesystems[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mkfs_builder_rejects_unsupported_options() {
+        let g = GuestFS::new();
+        let result = MkfsBuilder::new(FsType::Xfs)
+            .inode_size(256)
+            .create(&g, "/dev/sda");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_mkfs_builder_btrfs_subvolume_and_raid_profile() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+
+        MkfsBuilder::new(FsType::Btrfs)
+            .btrfs_raid_profile(BtrfsRaidProfile::Single, BtrfsRaidProfile::Single)
+            .btrfs_subvolume("root")
+            .create(&g, "/dev/sda")?;
+
+        // create_btrfs mounts "/" to create and set the default subvolume,
+        // then unmounts it again rather than leaving it mounted.
+        assert!(!g.is_mounted("/")?);
+
+        let filesystems = g.list_filesystems()?;
+        assert_eq!("/dev/sda", filesystems[0]);
+        assert_eq!("btrfs", filesystems[1]);
+
+        g.mount("/dev/sda", "/")?;
+        g.touch("/subvolume-test.txt")?;
+        let names: Vec<String> = g.read_dir("/")?.into_iter().map(|e| e.name).collect();
+        assert!(names.contains(&"subvolume-test.txt".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mkfs_builder_xfs() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+
+        MkfsBuilder::new(FsType::Xfs)
+            .label("test-label")
+            .create(&g, "/dev/sda")?;
+
+        let filesystems = g.list_filesystems()?;
+        assert_eq!("/dev/sda", filesystems[0]);
+        assert_eq!("xfs", filesystems[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mkfs_builder_vfat() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+
+        MkfsBuilder::new(FsType::Vfat).create(&g, "/dev/sda")?;
+
+        let filesystems = g.list_filesystems()?;
+        assert_eq!("/dev/sda", filesystems[0]);
+        assert_eq!("vfat", filesystems[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer_size must be greater than zero")]
+    fn test_open_options_rejects_zero_buffer_size() {
+        OpenOptions::new().buffer_size(0);
+    }
+
+    #[test]
+    fn test_unpack_layers_whiteout() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        let work = TempDir::new()?;
+
+        let layer1_dir = work.path_view().join("layer1");
+        fs::create_dir_all(layer1_dir.join("dir"))?;
+        fs::write(layer1_dir.join("foo.txt"), "layer1 foo")?;
+        fs::write(layer1_dir.join("dir/bar.txt"), "layer1 bar")?;
+        let layer1_tar = work.path_view().join("layer1.tar");
+        tar_dir(&layer1_dir, &layer1_tar, &["foo.txt", "dir"])?;
+
+        let layer2_dir = work.path_view().join("layer2");
+        fs::create_dir_all(layer2_dir.join("dir"))?;
+        fs::write(layer2_dir.join(".wh.foo.txt"), "")?;
+        fs::write(layer2_dir.join("dir/.wh..wh..opq"), "")?;
+        fs::write(layer2_dir.join("dir/newfile.txt"), "layer2 newfile")?;
+        let layer2_tar = work.path_view().join("layer2.tar");
+        tar_dir(&layer2_dir, &layer2_tar, &[".wh.foo.txt", "dir"])?;
+
+        g.unpack_layers(&[layer1_tar, layer2_tar], Path::new("/"))?;
+
+        let root_names: Vec<String> = g.read_dir("/")?.into_iter().map(|e| e.name).collect();
+        assert!(!root_names.contains(&"foo.txt".to_string()));
+
+        let dir_names: Vec<String> = g.read_dir("/dir")?.into_iter().map(|e| e.name).collect();
+        assert!(!dir_names.contains(&"bar.txt".to_string()));
+        assert!(dir_names.contains(&"newfile.txt".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_layers_whiteout_directory() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        let work = TempDir::new()?;
+
+        let layer1_dir = work.path_view().join("layer1");
+        fs::create_dir_all(layer1_dir.join("dir/subdir"))?;
+        fs::write(layer1_dir.join("dir/subdir/baz.txt"), "layer1 baz")?;
+        let layer1_tar = work.path_view().join("layer1.tar");
+        tar_dir(&layer1_dir, &layer1_tar, &["dir"])?;
+
+        let layer2_dir = work.path_view().join("layer2");
+        fs::create_dir_all(&layer2_dir.join("dir"))?;
+        fs::write(layer2_dir.join("dir/.wh.subdir"), "")?;
+        let layer2_tar = work.path_view().join("layer2.tar");
+        tar_dir(&layer2_dir, &layer2_tar, &["dir"])?;
+
+        g.unpack_layers(&[layer1_tar, layer2_tar], Path::new("/"))?;
+
+        let dir_names: Vec<String> = g.read_dir("/dir")?.into_iter().map(|e| e.name).collect();
+        assert!(!dir_names.contains(&"subdir".to_string()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpack_layers_nested_whiteout_under_opaque_directory() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+        g.add_drive(path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        let work = TempDir::new()?;
+
+        let layer1_dir = work.path_view().join("layer1");
+        fs::create_dir_all(layer1_dir.join("dir/sub"))?;
+        fs::write(layer1_dir.join("dir/sub/old.txt"), "layer1 old")?;
+        let layer1_tar = work.path_view().join("layer1.tar");
+        tar_dir(&layer1_dir, &layer1_tar, &["dir"])?;
+
+        // layer2 opaquely replaces "dir" and, in the same layer, recreates
+        // "dir/sub" with a file it immediately whites out again.
+        let layer2_dir = work.path_view().join("layer2");
+        fs::create_dir_all(layer2_dir.join("dir/sub"))?;
+        fs::write(layer2_dir.join("dir/.wh..wh..opq"), "")?;
+        fs::write(layer2_dir.join("dir/sub/keep2.txt"), "layer2 keep2")?;
+        fs::write(layer2_dir.join("dir/sub/.wh.keep2.txt"), "")?;
+        let layer2_tar = work.path_view().join("layer2.tar");
+        tar_dir(&layer2_dir, &layer2_tar, &["dir"])?;
+
+        g.unpack_layers(&[layer1_tar, layer2_tar], Path::new("/"))?;
+
+        // "dir/sub" survives the opaque cleanup (it was recreated by
+        // layer2), but the nested whiteout inside it must still be applied.
+        let sub_names: Vec<String> = g.read_dir("/dir/sub")?.into_iter().map(|e| e.name).collect();
+        assert!(!sub_names.contains(&"keep2.txt".to_string()));
+        assert!(!sub_names.contains(&".wh.keep2.txt".to_string()));
+
+        Ok(())
+    }
+
+    fn tar_dir(dir: &Path, out: &Path, entries: &[&str]) -> Result<()> {
+        let status = std::process::Command::new("tar")
+            .arg("-C")
+            .arg(dir)
+            .arg("-cf")
+            .arg(out)
+            .args(entries)
+            .status()?;
+        assert!(status.success());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_drive_options_format() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+
+        AddDriveOptions::new().format(DriveFormat::Raw).add(&g, path)?;
+        g.launch()?;
+        g.mount("/dev/sda", "/")?;
+
+        let filesystems = g.list_filesystems()?;
+        assert_eq!("/dev/sda", filesystems[0]);
+        assert_eq!("ext4", filesystems[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_drive_options_readonly() -> Result<()> {
+        let mut g = GuestFS::new();
+        let img = empty_image()?;
+        let path = &img.0;
+
+        AddDriveOptions::new()
+            .format(DriveFormat::Raw)
+            .readonly(true)
+            .add(&g, path)?;
+        g.launch()?;
+
+        assert!(g.mount("/dev/sda", "/").is_err());
+
+        Ok(())
+    }
+
     struct TempImage(PathBuf, TempDir);
 
     impl TempImage {
@@ -0,0 +1,313 @@
+//! A safe, fluent builder over the raw `guestfs_mke2fs_argv` / `guestfs_mkfs_opts`
+//! / `guestfs_mkfs_btrfs_argv` optargs structs.
+
+use std::path::Path;
+
+use eyre::Result;
+use libguestfs_sys::*;
+
+use crate::{GuestFS, LibGuestFsError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsType {
+    Ext2,
+    Ext3,
+    Ext4,
+    Btrfs,
+    Xfs,
+    Vfat,
+}
+
+impl FsType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FsType::Ext2 => "ext2",
+            FsType::Ext3 => "ext3",
+            FsType::Ext4 => "ext4",
+            FsType::Btrfs => "btrfs",
+            FsType::Xfs => "xfs",
+            FsType::Vfat => "vfat",
+        }
+    }
+
+    fn is_ext(&self) -> bool {
+        matches!(self, FsType::Ext2 | FsType::Ext3 | FsType::Ext4)
+    }
+}
+
+/// A btrfs data/metadata RAID profile, passed to `guestfs_mkfs_btrfs_argv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BtrfsRaidProfile {
+    Single,
+    Raid0,
+    Raid1,
+    Raid10,
+    Raid5,
+    Raid6,
+}
+
+impl BtrfsRaidProfile {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BtrfsRaidProfile::Single => "single",
+            BtrfsRaidProfile::Raid0 => "raid0",
+            BtrfsRaidProfile::Raid1 => "raid1",
+            BtrfsRaidProfile::Raid10 => "raid10",
+            BtrfsRaidProfile::Raid5 => "raid5",
+            BtrfsRaidProfile::Raid6 => "raid6",
+        }
+    }
+}
+
+/// A fluent builder for creating a filesystem without hand-constructing the
+/// raw C optargs struct for each filesystem type.
+pub struct MkfsBuilder {
+    fs_type: FsType,
+    block_size: Option<i64>,
+    inode_size: Option<i64>,
+    label: Option<String>,
+    uuid: Option<String>,
+    reserved_percentage: Option<i32>,
+    btrfs_subvolume: Option<String>,
+    btrfs_data_profile: Option<BtrfsRaidProfile>,
+    btrfs_metadata_profile: Option<BtrfsRaidProfile>,
+}
+
+impl MkfsBuilder {
+    pub fn new(fs_type: FsType) -> Self {
+        MkfsBuilder {
+            fs_type,
+            block_size: None,
+            inode_size: None,
+            label: None,
+            uuid: None,
+            reserved_percentage: None,
+            btrfs_subvolume: None,
+            btrfs_data_profile: None,
+            btrfs_metadata_profile: None,
+        }
+    }
+
+    pub fn block_size(mut self, block_size: i64) -> Self {
+        self.block_size = Some(block_size);
+        self
+    }
+
+    pub fn inode_size(mut self, inode_size: i64) -> Self {
+        self.inode_size = Some(inode_size);
+        self
+    }
+
+    pub fn label<S: Into<String>>(mut self, label: S) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn uuid<S: Into<String>>(mut self, uuid: S) -> Self {
+        self.uuid = Some(uuid.into());
+        self
+    }
+
+    /// Percentage of blocks reserved for the superuser. Only meaningful for
+    /// the ext2/ext3/ext4 family.
+    pub fn reserved_percentage(mut self, reserved_percentage: i32) -> Self {
+        self.reserved_percentage = Some(reserved_percentage);
+        self
+    }
+
+    /// Creates a named subvolume after formatting and makes it the default
+    /// subvolume. The device is mounted at `/` only while doing so and is
+    /// unmounted again afterwards. Only meaningful for btrfs.
+    pub fn btrfs_subvolume<S: Into<String>>(mut self, name: S) -> Self {
+        self.btrfs_subvolume = Some(name.into());
+        self
+    }
+
+    /// Sets the btrfs data and metadata RAID profiles. Only meaningful for
+    /// btrfs.
+    pub fn btrfs_raid_profile(mut self, data: BtrfsRaidProfile, metadata: BtrfsRaidProfile) -> Self {
+        self.btrfs_data_profile = Some(data);
+        self.btrfs_metadata_profile = Some(metadata);
+        self
+    }
+
+    /// Formats `device`, dispatching to the optargs struct matching the
+    /// configured filesystem type.
+    pub fn create<P: AsRef<Path>>(self, g: &GuestFS, device: P) -> Result<()> {
+        if !self.fs_type.is_ext() && self.reserved_percentage.is_some() {
+            return Err(unsupported(format!(
+                "reserved_percentage is not supported for {}",
+                self.fs_type.as_str()
+            )));
+        }
+        if self.fs_type != FsType::Btrfs
+            && (self.btrfs_subvolume.is_some()
+                || self.btrfs_data_profile.is_some()
+                || self.btrfs_metadata_profile.is_some())
+        {
+            return Err(unsupported(format!(
+                "btrfs-specific options are not supported for {}",
+                self.fs_type.as_str()
+            )));
+        }
+        if self.fs_type == FsType::Btrfs && self.block_size.is_some() {
+            return Err(unsupported(format!(
+                "block_size is not supported for {}",
+                self.fs_type.as_str()
+            )));
+        }
+        if !self.fs_type.is_ext() && self.inode_size.is_some() {
+            return Err(unsupported(format!(
+                "inode_size is not supported for {}",
+                self.fs_type.as_str()
+            )));
+        }
+
+        match self.fs_type {
+            FsType::Ext2 | FsType::Ext3 | FsType::Ext4 => self.create_ext(g, device.as_ref())?,
+            FsType::Btrfs => self.create_btrfs(g, device.as_ref())?,
+            FsType::Xfs | FsType::Vfat => self.create_mkfs_opts(g, device.as_ref())?,
+        }
+
+        if let Some(label) = &self.label {
+            g.set_label(device.as_ref(), label)?;
+        }
+        if let Some(uuid) = &self.uuid {
+            g.set_uuid(device.as_ref(), uuid)?;
+        }
+
+        Ok(())
+    }
+
+    fn create_ext<P: AsRef<Path>>(&self, g: &GuestFS, device: P) -> Result<()> {
+        let mut optargs: guestfs_mke2fs_argv = unsafe { std::mem::zeroed() };
+        let mut bitmask: u64 = 0;
+
+        let fstype_cstring = GuestFS::path_to_cstring_guest(self.fs_type.as_str())?;
+        optargs.fstype = fstype_cstring.as_ptr();
+        bitmask |= GUESTFS_MKE2FS_ARGV_FSTYPE_BITMASK as u64;
+
+        if let Some(block_size) = self.block_size {
+            optargs.blocksize = block_size;
+            bitmask |= GUESTFS_MKE2FS_ARGV_BLOCKSIZE_BITMASK as u64;
+        }
+        if let Some(inode_size) = self.inode_size {
+            optargs.inode_size = inode_size;
+            bitmask |= GUESTFS_MKE2FS_ARGV_INODE_SIZE_BITMASK as u64;
+        }
+        if let Some(reserved_percentage) = self.reserved_percentage {
+            optargs.reserved_percentage = reserved_percentage;
+            bitmask |= GUESTFS_MKE2FS_ARGV_RESERVED_PERCENTAGE_BITMASK as u64;
+        }
+        optargs.bitmask = bitmask;
+
+        g.mke2fs_argv(device, &optargs)
+    }
+
+    fn create_mkfs_opts<P: AsRef<Path>>(&self, g: &GuestFS, device: P) -> Result<()> {
+        let device = GuestFS::path_to_cstring_guest(device)?;
+        let fstype = GuestFS::path_to_cstring_guest(self.fs_type.as_str())?;
+
+        let mut optargs: guestfs_mkfs_opts = unsafe { std::mem::zeroed() };
+        let mut bitmask: u64 = 0;
+
+        if let Some(block_size) = self.block_size {
+            optargs.blocksize = block_size;
+            bitmask |= GUESTFS_MKFS_OPTS_BLOCKSIZE_BITMASK as u64;
+        }
+        optargs.bitmask = bitmask;
+
+        let out =
+            unsafe { guestfs_mkfs_opts(g.handle, fstype.as_ptr(), device.as_ptr(), &optargs) };
+        if out == 0 {
+            Ok(())
+        } else {
+            g.check_error()
+        }
+    }
+
+    fn create_btrfs(&self, g: &GuestFS, device: &Path) -> Result<()> {
+        let device_cstring = GuestFS::path_to_cstring_guest(device)?;
+        let devices = [device_cstring.as_ptr(), std::ptr::null()];
+
+        let mut optargs: guestfs_mkfs_btrfs_argv = unsafe { std::mem::zeroed() };
+        let mut bitmask: u64 = 0;
+
+        let datatype_cstring;
+        if let Some(profile) = self.btrfs_data_profile {
+            datatype_cstring = GuestFS::path_to_cstring_guest(profile.as_str())?;
+            optargs.datatype = datatype_cstring.as_ptr();
+            bitmask |= GUESTFS_MKFS_BTRFS_ARGV_DATATYPE_BITMASK as u64;
+        }
+        let metadata_cstring;
+        if let Some(profile) = self.btrfs_metadata_profile {
+            metadata_cstring = GuestFS::path_to_cstring_guest(profile.as_str())?;
+            optargs.metadata = metadata_cstring.as_ptr();
+            bitmask |= GUESTFS_MKFS_BTRFS_ARGV_METADATA_BITMASK as u64;
+        }
+        optargs.bitmask = bitmask;
+
+        let out = unsafe { guestfs_mkfs_btrfs_argv(g.handle, devices.as_ptr(), &optargs) };
+        if out != 0 {
+            return g.check_error();
+        }
+
+        if let Some(subvolume) = &self.btrfs_subvolume {
+            g.mount(device, "/")?;
+            let subvolume_path = Path::new("/").join(subvolume);
+            g.btrfs_subvolume_create(&subvolume_path)?;
+            g.btrfs_subvolume_set_default(&subvolume_path)?;
+            g.umount("/")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn unsupported(message: String) -> eyre::Report {
+    LibGuestFsError::UnsupportedOperation { errno: 0, message }.into()
+}
+
+impl GuestFS {
+    fn set_label<P: AsRef<Path>>(&self, device: P, label: &str) -> Result<()> {
+        let device = Self::path_to_cstring_guest(device)?;
+        let label = Self::path_to_cstring_guest(label)?;
+        let out = unsafe { guestfs_set_label(self.handle, device.as_ptr(), label.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    fn set_uuid<P: AsRef<Path>>(&self, device: P, uuid: &str) -> Result<()> {
+        let device = Self::path_to_cstring_guest(device)?;
+        let uuid = Self::path_to_cstring_guest(uuid)?;
+        let out = unsafe { guestfs_set_uuid(self.handle, device.as_ptr(), uuid.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    fn btrfs_subvolume_create<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = Self::path_to_cstring_guest(path)?;
+        let out = unsafe { guestfs_btrfs_subvolume_create(self.handle, path.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    fn btrfs_subvolume_set_default<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = Self::path_to_cstring_guest(path)?;
+        let out = unsafe { guestfs_btrfs_subvolume_set_default(self.handle, path.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+}
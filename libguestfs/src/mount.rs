@@ -0,0 +1,171 @@
+//! Mount table introspection and guest OS auto-detection.
+
+use std::ffi::CStr;
+use std::path::PathBuf;
+
+use eyre::Result;
+use libguestfs_sys::*;
+
+use crate::GuestFS;
+
+/// A detected guest operating system, as produced by [`GuestFS::inspect_os`].
+#[derive(Debug, Clone)]
+pub struct GuestOs {
+    pub root: String,
+    pub os_type: String,
+    pub distro: String,
+    pub product_name: String,
+    /// This OS's mountpoints, as `(device, mountpoint)` pairs, matching the
+    /// order used by [`GuestFS::mountpoints`].
+    pub mountpoints: Vec<(String, PathBuf)>,
+}
+
+impl GuestFS {
+    /// Returns the current mount table as `(device, mountpoint)` pairs.
+    pub fn mountpoints(&self) -> Result<Vec<(String, PathBuf)>> {
+        let flat = self.flat_string_list(unsafe { guestfs_mountpoints(self.handle) })?;
+        Ok(flat
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), PathBuf::from(&pair[1])))
+            .collect())
+    }
+
+    /// Returns the devices that are currently mounted, in mount order.
+    pub fn mounts(&self) -> Result<Vec<String>> {
+        self.flat_string_list(unsafe { guestfs_mounts(self.handle) })
+    }
+
+    /// Returns `true` if `mountpoint` is in the current mount table.
+    pub fn is_mounted<P: AsRef<std::path::Path>>(&self, mountpoint: P) -> Result<bool> {
+        let mountpoint = mountpoint.as_ref();
+        Ok(self
+            .mountpoints()?
+            .iter()
+            .any(|(_, mp)| mp == mountpoint))
+    }
+
+    /// Unmounts every currently mounted filesystem.
+    pub fn umount_all(&self) -> Result<()> {
+        let out = unsafe { guestfs_umount_all(self.handle) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Runs libguestfs's OS inspection and returns the first detected guest,
+    /// if any.
+    pub fn inspect_os(&self) -> Result<Option<GuestOs>> {
+        let roots = self.flat_string_list(unsafe { guestfs_inspect_os(self.handle) })?;
+        let Some(root) = roots.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let root_cstr = Self::path_to_cstring_guest(&root)?;
+
+        let os_type_ptr = unsafe { guestfs_inspect_get_type(self.handle, root_cstr.as_ptr()) };
+        let os_type = self.take_string(os_type_ptr)?;
+
+        let distro_ptr = unsafe { guestfs_inspect_get_distro(self.handle, root_cstr.as_ptr()) };
+        let distro = self.take_string(distro_ptr)?;
+
+        let product_name_ptr =
+            unsafe { guestfs_inspect_get_product_name(self.handle, root_cstr.as_ptr()) };
+        let product_name = self.take_string(product_name_ptr)?;
+
+        let flat_mountpoints = self.flat_string_list(unsafe {
+            guestfs_inspect_get_mountpoints(self.handle, root_cstr.as_ptr())
+        })?;
+        let mountpoints = flat_mountpoints
+            .chunks(2)
+            .map(|pair| (pair[1].clone(), PathBuf::from(&pair[0])))
+            .collect();
+
+        Ok(Some(GuestOs {
+            root,
+            os_type,
+            distro,
+            product_name,
+            mountpoints,
+        }))
+    }
+
+    /// Mounts every mountpoint reported by [`GuestFS::inspect_os`], shortest
+    /// path first so that `/` is mounted before `/boot`, `/home`, etc.
+    pub fn auto_mount(&self, read_only: bool) -> Result<GuestOs> {
+        let guest_os = self
+            .inspect_os()?
+            .ok_or_else(|| eyre::eyre!("no guest operating system detected"))?;
+
+        let mut mountpoints = guest_os.mountpoints.clone();
+        mountpoints.sort_by_key(|(_, path)| path.as_os_str().len());
+
+        for (device, mountpoint) in &mountpoints {
+            if read_only {
+                self.mount_ro(device, mountpoint)?;
+            } else {
+                self.mount(device, mountpoint)?;
+            }
+        }
+
+        Ok(guest_os)
+    }
+
+    fn mount_ro<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+        &self,
+        mountable: P,
+        mountpoint: Q,
+    ) -> Result<()> {
+        let mountable = Self::path_to_cstring_guest(mountable)?;
+        let mountpoint = Self::path_to_cstring_guest(mountpoint)?;
+        let out =
+            unsafe { guestfs_mount_ro(self.handle, mountable.as_ptr(), mountpoint.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Unmounts a single mountpoint, leaving any other mounted filesystems
+    /// untouched (unlike [`GuestFS::umount_all`]).
+    pub(crate) fn umount<P: AsRef<std::path::Path>>(&self, mountpoint: P) -> Result<()> {
+        let mountpoint = Self::path_to_cstring_guest(mountpoint)?;
+        let out = unsafe { guestfs_umount(self.handle, mountpoint.as_ptr()) };
+        if out == 0 {
+            Ok(())
+        } else {
+            self.check_error()
+        }
+    }
+
+    /// Collects a NULL-terminated `char**` into owned `String`s, freeing the
+    /// libguestfs-allocated strings and the list itself as it goes.
+    fn flat_string_list(&self, list_ptr: *mut *mut i8) -> Result<Vec<String>> {
+        if list_ptr.is_null() {
+            return self.check_error();
+        }
+        let string_count = self.count_strings(list_ptr as *const *const i8);
+        let entries = unsafe { std::slice::from_raw_parts(list_ptr, string_count) };
+
+        let mut out = Vec::with_capacity(entries.len());
+        for ptr in entries {
+            let value = unsafe { CStr::from_ptr(*ptr) }.to_str()?.to_string();
+            out.push(value);
+            self.free(*ptr);
+        }
+        self.free(list_ptr as *mut i8);
+
+        Ok(out)
+    }
+
+    fn take_string(&self, ptr: *mut i8) -> Result<String> {
+        if ptr.is_null() {
+            return self.check_error();
+        }
+        let value = unsafe { CStr::from_ptr(ptr) }.to_str()?.to_string();
+        self.free(ptr);
+        Ok(value)
+    }
+}